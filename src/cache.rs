@@ -0,0 +1,140 @@
+//! A disk-backed cache for memoizing expensive computations between script filter invocations,
+//! mirroring the `maxAge`-based cache used by [alfy](https://github.com/sindresorhus/alfy)
+//! workflows.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::Read as IoRead;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use errors::*;
+
+/// The environment variable Alfred sets to its per-workflow cache directory.
+const CACHE_DIR_VAR: &str = "alfred_workflow_cache";
+
+/// The name of the JSON file the cache is persisted to within the cache directory.
+const CACHE_FILE_NAME: &str = "rusty_alfred_cache.json";
+
+/// A single cached entry: the serialized value, the millisecond Unix timestamp it was stored at,
+/// and the max age (in milliseconds) it was stored with.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+  value: Value,
+  timestamp: u64,
+  max_age_ms: u64
+}
+
+/// A disk-backed cache for memoizing expensive computations between script filter invocations.
+///
+/// Entries are persisted as a single JSON map to a file under Alfred's per-workflow cache
+/// directory (the `alfred_workflow_cache` environment variable), falling back to the system temp
+/// directory if that variable isn't set. Each entry remembers the max age it was stored with, so
+/// a later `get` knows whether it has gone stale.
+#[derive(Debug)]
+pub struct Cache {
+  path: PathBuf
+}
+
+impl Default for Cache {
+  fn default() -> Self {
+    let mut dir = env::var(CACHE_DIR_VAR).map(PathBuf::from).unwrap_or_else(|_| env::temp_dir());
+    dir.push(CACHE_FILE_NAME);
+    Cache { path: dir }
+  }
+}
+
+impl Cache {
+  /// Opens the cache backed by Alfred's per-workflow cache directory.
+  pub fn new() -> Self {
+    Cache::default()
+  }
+
+  /// Opens the cache at an explicit path, primarily useful for testing outside Alfred.
+  pub fn with_path<P: Into<PathBuf>>(path: P) -> Self {
+    Cache { path: path.into() }
+  }
+
+  /// Returns the value stored at `key` if it exists and is no older than the max age it was
+  /// `set` with.
+  pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+    let entries = self.read_entries();
+    let entry = entries.get(key)?;
+    if Cache::now_ms().saturating_sub(entry.timestamp) > entry.max_age_ms {
+      return None;
+    }
+    serde_json::from_value(entry.value.clone()).ok()
+  }
+
+  /// Returns the value stored at `key` regardless of how long ago it was stored, or `None` if
+  /// nothing is stored there.
+  pub fn get_ignore_max_age<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+    let entries = self.read_entries();
+    let entry = entries.get(key)?;
+    serde_json::from_value(entry.value.clone()).ok()
+  }
+
+  /// Stores `value` at `key` with the current timestamp, valid until `max_age` elapses.
+  pub fn set<T: Serialize>(&self, key: &str, value: T, max_age: Duration) -> Result<()> {
+    let mut entries = self.read_entries();
+    let value = serde_json::to_value(value).chain_err(|| "could not serialize cache value")?;
+    entries.insert(key.to_owned(), CacheEntry {
+      value,
+      timestamp: Cache::now_ms(),
+      max_age_ms: duration_to_ms(max_age)
+    });
+    self.write_entries(&entries)
+  }
+
+  /// Returns whether `key` is missing or older than the max age it was stored with.
+  pub fn is_expired(&self, key: &str) -> bool {
+    let entries = self.read_entries();
+    match entries.get(key) {
+      Some(entry) => Cache::now_ms().saturating_sub(entry.timestamp) > entry.max_age_ms,
+      None => true
+    }
+  }
+
+  /// Reads the cache file from disk, treating a missing or corrupt file as an empty cache rather
+  /// than panicking or returning an error.
+  fn read_entries(&self) -> HashMap<String, CacheEntry> {
+    let mut file = match File::open(&self.path) {
+      Ok(file) => file,
+      Err(_) => return HashMap::new()
+    };
+    let mut contents = String::new();
+    if file.read_to_string(&mut contents).is_err() {
+      return HashMap::new();
+    }
+    serde_json::from_str(&contents).unwrap_or_else(|_| HashMap::new())
+  }
+
+  /// Writes the cache file to disk atomically, by writing to a temporary file and renaming it
+  /// into place, so a killed script can't corrupt the store.
+  fn write_entries(&self, entries: &HashMap<String, CacheEntry>) -> Result<()> {
+    if let Some(parent) = self.path.parent() {
+      fs::create_dir_all(parent).chain_err(|| "could not create cache directory")?;
+    }
+    let serialized = serde_json::to_string(entries).chain_err(|| "could not serialize cache entries")?;
+    let tmp_path = self.path.with_extension("json.tmp");
+    fs::write(&tmp_path, serialized).chain_err(|| "could not write temporary cache file")?;
+    fs::rename(&tmp_path, &self.path).chain_err(|| "could not move temporary cache file into place")?;
+    Ok(())
+  }
+
+  /// The current Unix timestamp, in milliseconds.
+  fn now_ms() -> u64 {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_else(|_| Duration::from_secs(0));
+    duration_to_ms(since_epoch)
+  }
+}
+
+/// Converts a `Duration` to whole milliseconds.
+fn duration_to_ms(duration: Duration) -> u64 {
+  duration.as_secs() * 1000 + u64::from(duration.subsec_nanos() / 1_000_000)
+}
@@ -0,0 +1,49 @@
+//! Reads the environment variables Alfred injects into every script filter invocation, exposing
+//! them as a single typed entry point instead of ad-hoc `std::env::var` calls.
+
+use std::env;
+
+/// The environment Alfred provides to a running script filter: the workflow's identity, its
+/// cache/data directories, the running Alfred version, and the current `{query}`.
+///
+/// Missing variables yield `None`/defaults rather than errors, so this type is usable in tests
+/// outside Alfred.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Workflow {
+  /// The workflow's bundle id (`alfred_workflow_bundleid`).
+  pub bundle_id: Option<String>,
+  /// The workflow's name (`alfred_workflow_name`).
+  pub name: Option<String>,
+  /// The workflow's version (`alfred_workflow_version`).
+  pub version: Option<String>,
+  /// The workflow's unique id (`alfred_workflow_uid`).
+  pub uid: Option<String>,
+  /// The workflow's cache directory, which the `Cache` type also reads (`alfred_workflow_cache`).
+  pub cache_dir: Option<String>,
+  /// The workflow's data directory (`alfred_workflow_data`).
+  pub data_dir: Option<String>,
+  /// The running Alfred version (`alfred_version`).
+  pub alfred_version: Option<String>,
+  /// Whether Alfred's debugger is open for this workflow (`alfred_debug`).
+  pub debug: bool,
+  /// The current `{query}` passed to the script filter, if any.
+  pub query: Option<String>
+}
+
+impl Workflow {
+  /// Reads the environment variables (and the `{query}` argument) Alfred provides to a running
+  /// script filter. Missing variables yield `None`/defaults rather than errors.
+  pub fn from_env() -> Self {
+    Workflow {
+      bundle_id: env::var("alfred_workflow_bundleid").ok(),
+      name: env::var("alfred_workflow_name").ok(),
+      version: env::var("alfred_workflow_version").ok(),
+      uid: env::var("alfred_workflow_uid").ok(),
+      cache_dir: env::var("alfred_workflow_cache").ok(),
+      data_dir: env::var("alfred_workflow_data").ok(),
+      alfred_version: env::var("alfred_version").ok(),
+      debug: env::var("alfred_debug").map(|debug| debug == "1").unwrap_or(false),
+      query: env::args().nth(1)
+    }
+  }
+}
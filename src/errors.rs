@@ -0,0 +1,3 @@
+//! Errors using [error-chain](https://crates.io/crates/error_chain).
+
+error_chain! {}
@@ -27,23 +27,107 @@ extern crate serde_derive;
 #[macro_use]
 extern crate error_chain;
 
+use std::collections::HashMap;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
 /// Errors using [error-chain](https://crates.io/crates/error_chain).
 pub mod errors;
 
+/// A disk-backed cache for memoizing expensive computations between script filter invocations.
+pub mod cache;
+
+/// An alternate, Alfred 2 compatible XML output format.
+pub mod xml;
+
+/// Reads the environment Alfred injects into every script filter invocation.
+pub mod env;
+
 use errors::*;
 
+/// Returns `true` if `variables` is `None` or an empty map, so it can be used with
+/// `skip_serializing_if` to only emit the `variables` key when there's something to send.
+fn variables_is_empty(variables: &Option<HashMap<String, String>>) -> bool {
+  variables.as_ref().map(HashMap::is_empty).unwrap_or(true)
+}
+
+/// One or more arguments passed through to the connected output action.
+///
+/// Alfred accepts `arg` as either a single string or an array of strings, so this serializes as
+/// a bare string when there is exactly one value, and as an array when there are several.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AlfredItemArgs(pub(crate) Vec<String>);
+
+impl AlfredItemArgs {
+  /// Wraps a single argument.
+  fn single<T>(arg: T) -> Self
+    where T: AsRef<str>
+  {
+    AlfredItemArgs(vec![arg.as_ref().to_owned()])
+  }
+
+  /// Wraps many arguments.
+  fn many<I, T>(args: I) -> Self
+    where I: IntoIterator<Item = T>, T: AsRef<str>
+  {
+    AlfredItemArgs(args.into_iter().map(|arg| arg.as_ref().to_owned()).collect())
+  }
+}
+
+impl Serialize for AlfredItemArgs {
+  fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where S: Serializer
+  {
+    match self.0.len() {
+      1 => serializer.serialize_str(&self.0[0]),
+      _ => self.0.serialize(serializer)
+    }
+  }
+}
+
+impl<'de> Deserialize<'de> for AlfredItemArgs {
+  fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+    where D: Deserializer<'de>
+  {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+      One(String),
+      Many(Vec<String>)
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+      OneOrMany::One(arg) => AlfredItemArgs(vec![arg]),
+      OneOrMany::Many(args) => AlfredItemArgs(args)
+    })
+  }
+}
+
 /// The parent for all Alfred items. This is what should be printed to `stdout` via `to_json()` for
 /// Alfred to display results.
-#[derive(Debug, PartialEq, Hash, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct AlfredItems {
   /// The items to be displayed in Alfred.
-  pub items: Vec<AlfredItem>
+  pub items: Vec<AlfredItem>,
+  /// Variables passed out of the script filter which become workflow variables available to
+  /// downstream workflow objects.
+  #[serde(skip_serializing_if = "variables_is_empty")]
+  pub variables: Option<HashMap<String, String>>,
+  /// The number of seconds (between `0.1` and `5.0`) after which Alfred should re-run the script
+  /// filter, letting a long-running task update its results in place.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub rerun: Option<f64>
 }
 
+/// The range of seconds, inclusive, that Alfred accepts for the `rerun` key. Values outside this
+/// window are silently ignored by Alfred, so `to_json` rejects them instead.
+const RERUN_RANGE: (f64, f64) = (0.1, 5.0);
+
 impl Default for AlfredItems {
   fn default() -> Self {
     AlfredItems {
-      items: Vec::new()
+      items: Vec::new(),
+      variables: None,
+      rerun: None
     }
   }
 }
@@ -61,18 +145,61 @@ impl AlfredItems {
     self
   }
 
+  /// Sets the workflow variables passed out of the script filter.
+  pub fn variables(mut self, variables: HashMap<String, String>) -> Self {
+    self.variables = Some(variables);
+    self
+  }
+
+  /// Sets a single workflow variable, adding to any variables already set.
+  pub fn variable<K, V>(mut self, key: K, value: V) -> Self
+    where K: AsRef<str>, V: AsRef<str>
+  {
+    self.variables.get_or_insert_with(HashMap::new).insert(key.as_ref().to_owned(), value.as_ref().to_owned());
+    self
+  }
+
+  /// Sets the interval, in seconds, after which Alfred should automatically re-run the script
+  /// filter. Must be between `0.1` and `5.0`; out-of-range values are caught by `to_json` rather
+  /// than here, since Alfred would otherwise silently ignore them.
+  pub fn rerun(mut self, secs: f64) -> Self {
+    self.rerun = Some(secs);
+    self
+  }
+
   /// Attempts to use serde to convert this container to JSON. The resulting JSON is ready to be
   /// given to Alfred.
   pub fn to_json(&self) -> Result<String> {
+    if let Some(secs) = self.rerun {
+      let (min, max) = RERUN_RANGE;
+      if secs < min || secs > max {
+        bail!("rerun must be between {} and {} seconds, got {}", min, max, secs);
+      }
+    }
     serde_json::to_string(self).chain_err(|| "could not serialize AlfredItems")
   }
+
+  /// Converts this container to the Alfred 2 XML script filter format, for workflows that still
+  /// need to target Alfred 2. Only the fields Alfred 2 understands are emitted; Alfred 3-only
+  /// features like `variables`, `rerun`, and modifiers are not part of that schema.
+  pub fn to_xml(&self) -> Result<String> {
+    let mut buf = Vec::new();
+    {
+      let mut writer = xml::XmlWriter::new(&mut buf);
+      for item in &self.items {
+        writer.write_item(item)?;
+      }
+      writer.close()?;
+    }
+    String::from_utf8(buf).chain_err(|| "could not convert XML output to a string")
+  }
 }
 
 /// An item to be displayed in Alfred. Only the `title` attribute is required.
 ///
 /// Documentation mostly copied from
 /// [here](https://www.alfredapp.com/help/workflows/inputs/script-filter/json/).
-#[derive(Debug, PartialEq, Hash, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct AlfredItem {
   /// This is a unique identifier for the item which allows help Alfred to learn about this item for
   /// subsequent sorting and ordering of the user's actioned results.
@@ -88,13 +215,14 @@ pub struct AlfredItem {
   /// The subtitle displayed in the result row. This element is optional.
   #[serde(skip_serializing_if = "Option::is_none")]
   pub subtitle: Option<String>,
-  /// The argument which is passed through the workflow to the connected output action.
+  /// The argument(s) which are passed through the workflow to the connected output action.
   ///
   /// While the arg attribute is optional, it's highly recommended that you populate this as it's
-  /// the string which is passed to your connected output actions. If excluded, you won't know which
-  /// result item the user has selected.
+  /// what's passed to your connected output actions. If excluded, you won't know which result item
+  /// the user has selected. A single argument serializes as a bare string; multiple arguments
+  /// serialize as an array, fanning the selection out to multiple downstream arguments.
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub arg: Option<String>,
+  pub arg: Option<AlfredItemArgs>,
   /// The icon displayed in the result row. Workflows are run from their workflow folder,
   /// so you can reference icons stored in your workflow relatively.
   ///
@@ -131,7 +259,11 @@ pub struct AlfredItem {
   /// A Quick Look URL which will be visible if the user uses the Quick Look feature within Alfred
   /// (tapping shift, or cmd+y)
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub quicklookurl: Option<String>
+  pub quicklookurl: Option<String>,
+  /// Variables passed out of this item which become workflow variables available to downstream
+  /// workflow objects.
+  #[serde(skip_serializing_if = "variables_is_empty")]
+  pub variables: Option<HashMap<String, String>>
 }
 
 impl AlfredItem {
@@ -152,7 +284,8 @@ impl AlfredItem {
       item_type: None,
       item_mods: None,
       text: None,
-      quicklookurl: None
+      quicklookurl: None,
+      variables: None
     }
   }
 
@@ -189,12 +322,21 @@ impl AlfredItem {
   /// The argument which is passed through the workflow to the connected output action.
   ///
   /// While the arg attribute is optional, it's highly recommended that you populate this as it's
-  /// the string which is passed to your connected output actions. If excluded, you won't know which
-  /// result item the user has selected.
+  /// what's passed to your connected output actions. If excluded, you won't know which result item
+  /// the user has selected.
   pub fn arg<T>(mut self, arg: T) -> Self
     where T: AsRef<str>
   {
-    self.arg = Some(arg.as_ref().to_owned());
+    self.arg = Some(AlfredItemArgs::single(arg));
+    self
+  }
+
+  /// Multiple arguments which are passed together through the workflow to the connected output
+  /// action, fanning a single selection out to multiple downstream arguments.
+  pub fn args<I, T>(mut self, args: I) -> Self
+    where I: IntoIterator<Item = T>, T: AsRef<str>
+  {
+    self.arg = Some(AlfredItemArgs::many(args));
     self
   }
 
@@ -258,6 +400,20 @@ impl AlfredItem {
     self.quicklookurl = Some(quicklookurl.as_ref().to_owned());
     self
   }
+
+  /// Sets the workflow variables passed out of this item.
+  pub fn variables(mut self, variables: HashMap<String, String>) -> Self {
+    self.variables = Some(variables);
+    self
+  }
+
+  /// Sets a single workflow variable on this item, adding to any variables already set.
+  pub fn variable<K, V>(mut self, key: K, value: V) -> Self
+    where K: AsRef<str>, V: AsRef<str>
+  {
+    self.variables.get_or_insert_with(HashMap::new).insert(key.as_ref().to_owned(), value.as_ref().to_owned());
+    self
+  }
 }
 
 /// Types for the `type` field on an `AlfredItemType`.
@@ -458,17 +614,24 @@ impl AlfredItemMods {
 }
 
 /// Information about a specific modifier to an item.
-#[derive(Debug, PartialEq, Hash, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct AlfredItemMod {
   /// Marks if the result is valid based on the modifier selection.
   #[serde(skip_serializing_if = "Option::is_none")]
   pub valid: Option<bool>,
-  /// The arg to be passed out if actioned with the modifier.
+  /// The argument(s) to be passed out if actioned with the modifier.
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub arg: Option<String>,
+  pub arg: Option<AlfredItemArgs>,
   /// The subtitle to be displayed while the modifier is pressed.
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub subtitle: Option<String>
+  pub subtitle: Option<String>,
+  /// The icon to swap in while the modifier is pressed.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub icon: Option<AlfredItemIcon>,
+  /// Variables passed out of this modifier which become workflow variables available to
+  /// downstream workflow objects.
+  #[serde(skip_serializing_if = "variables_is_empty")]
+  pub variables: Option<HashMap<String, String>>
 }
 
 impl Default for AlfredItemMod {
@@ -476,7 +639,9 @@ impl Default for AlfredItemMod {
     AlfredItemMod {
       valid: None,
       arg: None,
-      subtitle: None
+      subtitle: None,
+      icon: None,
+      variables: None
     }
   }
 }
@@ -499,7 +664,16 @@ impl AlfredItemMod {
   pub fn arg<T>(mut self, arg: T) -> Self
     where T: AsRef<str>
   {
-    self.arg = Some(arg.as_ref().to_owned());
+    self.arg = Some(AlfredItemArgs::single(arg));
+    self
+  }
+
+  /// Multiple arguments to be passed together out if actioned with the modifier, fanning a single
+  /// selection out to multiple downstream arguments.
+  pub fn args<I, T>(mut self, args: I) -> Self
+    where I: IntoIterator<Item = T>, T: AsRef<str>
+  {
+    self.arg = Some(AlfredItemArgs::many(args));
     self
   }
 
@@ -510,4 +684,24 @@ impl AlfredItemMod {
     self.subtitle = Some(subtitle.as_ref().to_owned());
     self
   }
+
+  /// The icon to swap in while the modifier is pressed.
+  pub fn icon(mut self, icon: AlfredItemIcon) -> Self {
+    self.icon = Some(icon);
+    self
+  }
+
+  /// Sets the workflow variables passed out of this modifier.
+  pub fn variables(mut self, variables: HashMap<String, String>) -> Self {
+    self.variables = Some(variables);
+    self
+  }
+
+  /// Sets a single workflow variable on this modifier, adding to any variables already set.
+  pub fn variable<K, V>(mut self, key: K, value: V) -> Self
+    where K: AsRef<str>, V: AsRef<str>
+  {
+    self.variables.get_or_insert_with(HashMap::new).insert(key.as_ref().to_owned(), value.as_ref().to_owned());
+    self
+  }
 }
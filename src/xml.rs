@@ -0,0 +1,131 @@
+//! Alfred 2's script filters consume XML rather than JSON. This module renders the same
+//! `AlfredItem` builder types to that older schema, either all at once via `AlfredItems::to_xml`
+//! or one item at a time via `XmlWriter`.
+
+use std::io::Write;
+
+use errors::*;
+use {AlfredItem, AlfredItemIconType, AlfredItemType};
+
+/// Streaming writer for Alfred 2's XML script filter format, writing one `<item>` at a time to
+/// any `io::Write` and flushing when `close()` is called.
+pub struct XmlWriter<W: Write> {
+  writer: W,
+  opened: bool,
+  closed: bool
+}
+
+impl<W: Write> XmlWriter<W> {
+  /// Creates a new XML writer around `writer`. Nothing is written until the first item (or
+  /// `close()`) is given to it.
+  pub fn new(writer: W) -> Self {
+    XmlWriter {
+      writer,
+      opened: false,
+      closed: false
+    }
+  }
+
+  /// Writes the XML declaration and the opening `<items>` tag, if it hasn't already happened.
+  fn open(&mut self) -> Result<()> {
+    if !self.opened {
+      writeln!(self.writer, "<?xml version=\"1.0\"?>").chain_err(|| "could not write XML declaration")?;
+      writeln!(self.writer, "<items>").chain_err(|| "could not write <items> root element")?;
+      self.opened = true;
+    }
+    Ok(())
+  }
+
+  /// Writes a single item, opening the `<items>` root the first time this is called.
+  pub fn write_item(&mut self, item: &AlfredItem) -> Result<()> {
+    self.open()?;
+    write_item(&mut self.writer, item)
+  }
+
+  /// Closes the `<items>` root element and flushes the underlying writer. Safe to call more than
+  /// once; later calls are no-ops besides the flush.
+  pub fn close(&mut self) -> Result<()> {
+    self.open()?;
+    if !self.closed {
+      writeln!(self.writer, "</items>").chain_err(|| "could not write closing </items> element")?;
+      self.closed = true;
+    }
+    self.writer.flush().chain_err(|| "could not flush XML writer")
+  }
+}
+
+/// Writes a single `<item>` element, including its `<title>`, `<subtitle>`, `<icon>`, and `<text>`
+/// children. Fields that are `None` are omitted entirely.
+fn write_item<W: Write>(writer: &mut W, item: &AlfredItem) -> Result<()> {
+  write!(writer, "<item").chain_err(|| "could not write <item> opening tag")?;
+  if let Some(ref uid) = item.uid {
+    write!(writer, " uid=\"{}\"", escape_attr(uid)).chain_err(|| "could not write item uid attribute")?;
+  }
+  if let Some(ref arg) = item.arg {
+    // Alfred 2's XML schema only has room for a single arg string, unlike Alfred 3's JSON arrays,
+    // so multiple arguments are joined with newlines the way Alfred itself splits a multi-line arg.
+    write!(writer, " arg=\"{}\"", escape_attr(&arg.0.join("\n"))).chain_err(|| "could not write item arg attribute")?;
+  }
+  if let Some(valid) = item.valid {
+    write!(writer, " valid=\"{}\"", if valid { "yes" } else { "no" })
+      .chain_err(|| "could not write item valid attribute")?;
+  }
+  if let Some(ref autocomplete) = item.autocomplete {
+    write!(writer, " autocomplete=\"{}\"", escape_attr(autocomplete))
+      .chain_err(|| "could not write item autocomplete attribute")?;
+  }
+  if let Some(ref item_type) = item.item_type {
+    write!(writer, " type=\"{}\"", item_type_str(item_type)).chain_err(|| "could not write item type attribute")?;
+  }
+  write!(writer, ">").chain_err(|| "could not close <item> opening tag")?;
+
+  write!(writer, "<title>{}</title>", escape_text(&item.title)).chain_err(|| "could not write item title")?;
+  if let Some(ref subtitle) = item.subtitle {
+    write!(writer, "<subtitle>{}</subtitle>", escape_text(subtitle)).chain_err(|| "could not write item subtitle")?;
+  }
+  if let Some(ref icon) = item.icon {
+    write!(writer, "<icon").chain_err(|| "could not write <icon> opening tag")?;
+    if let Some(ref icon_type) = icon.icon_type {
+      write!(writer, " type=\"{}\"", icon_type_str(icon_type)).chain_err(|| "could not write icon type attribute")?;
+    }
+    write!(writer, ">{}</icon>", escape_text(&icon.path)).chain_err(|| "could not write icon path")?;
+  }
+  if let Some(ref text) = item.text {
+    if let Some(ref copy) = text.copy {
+      write!(writer, "<text type=\"copy\">{}</text>", escape_text(copy)).chain_err(|| "could not write item copy text")?;
+    }
+    if let Some(ref largetype) = text.largetype {
+      write!(writer, "<text type=\"largetype\">{}</text>", escape_text(largetype))
+        .chain_err(|| "could not write item largetype text")?;
+    }
+  }
+
+  writeln!(writer, "</item>").chain_err(|| "could not write </item> closing tag")
+}
+
+/// The Alfred 2 XML `type` attribute value for an item type.
+fn item_type_str(item_type: &AlfredItemType) -> &'static str {
+  match *item_type {
+    AlfredItemType::Default => "default",
+    AlfredItemType::File => "file",
+    AlfredItemType::FileSkipCheck => "file:skipcheck"
+  }
+}
+
+/// The Alfred 2 XML `type` attribute value for an icon type.
+fn icon_type_str(icon_type: &AlfredItemIconType) -> &'static str {
+  match *icon_type {
+    AlfredItemIconType::FileIcon => "fileicon",
+    AlfredItemIconType::FileType => "filetype"
+  }
+}
+
+/// Escapes text for use as XML element content.
+fn escape_text(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Escapes text for use inside a double-quoted XML attribute value.
+fn escape_attr(text: &str) -> String {
+  escape_text(text).replace('"', "&quot;")
+}